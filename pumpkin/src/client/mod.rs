@@ -0,0 +1,171 @@
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+use pumpkin_core::text::TextComponent;
+
+use crate::config::ProxyMode;
+use crate::proxy::{self, BungeeForwarding, ForwardedPlayerInfo, HaProxyHeader};
+use crate::server::Server;
+
+/// Generous upper bound on a Bungee/Velocity forwarded server-address field
+/// (`host\0ip\0uuid\0properties`), in case a peer never sends one of the
+/// `\0` separators `parse_bungee_forwarding` is waiting on.
+const MAX_BUNGEE_FIELD_LEN: usize = 4096;
+
+/// A connection before (and, once it becomes a `Player`, still during) play.
+/// Owns the socket directly; `apply_proxy_header` and `process_packets` are
+/// the only things that read from it, `send_raw`/`kick` the only things that
+/// write to it.
+pub struct Client {
+    connection: TcpStream,
+    pub address: SocketAddr,
+    outbound_tx: UnboundedSender<Vec<u8>>,
+    read_buffer: Vec<u8>,
+    /// Set by `apply_proxy_header`; `decode_buffered_packets` checks this to
+    /// know whether to look for a forwarded server-address field.
+    proxy_mode: ProxyMode,
+    /// Identity BungeeCord/Velocity forwarded for this connection, if any.
+    /// `Server::add_player` skips online-mode auth when this is set, since
+    /// the proxy already authenticated the player with Mojang.
+    pub forwarded_profile: Option<ForwardedPlayerInfo>,
+    pub closed: bool,
+    pub make_player: bool,
+}
+
+impl Client {
+    pub fn new(
+        connection: TcpStream,
+        address: SocketAddr,
+        outbound_tx: UnboundedSender<Vec<u8>>,
+    ) -> Self {
+        Self {
+            connection,
+            address,
+            outbound_tx,
+            read_buffer: Vec::new(),
+            proxy_mode: ProxyMode::Disabled,
+            forwarded_profile: None,
+            closed: false,
+            make_player: false,
+        }
+    }
+
+    /// A clone of this client's outbound sender, for `Server::register_connection`.
+    pub fn outbound_sender(&self) -> UnboundedSender<Vec<u8>> {
+        self.outbound_tx.clone()
+    }
+
+    /// Checks for the proxy header `mode` expects at the start of the
+    /// connection and, if found, overwrites `self.address` with the real
+    /// client address. Must be called before any handshake bytes are handed
+    /// to `process_packets`.
+    ///
+    /// HAProxy's header arrives before the handshake packet, so it is read
+    /// and consumed here, straight into `read_buffer`. Bungee/Velocity
+    /// forwarding instead rides inside the handshake's server-address field,
+    /// so for that mode this only records `mode` for `decode_buffered_packets`
+    /// to act on once the handshake itself is decoded.
+    pub async fn apply_proxy_header(&mut self, mode: ProxyMode) -> io::Result<()> {
+        self.proxy_mode = mode;
+        if mode != ProxyMode::HaProxy {
+            return Ok(());
+        }
+
+        loop {
+            match proxy::parse_haproxy_v1(&self.read_buffer) {
+                HaProxyHeader::Present(address, header_len) => {
+                    self.read_buffer.drain(..header_len);
+                    self.address = address;
+                    return Ok(());
+                }
+                HaProxyHeader::NotProxy => return Ok(()),
+                HaProxyHeader::Incomplete => {
+                    // Actually read (and await) more bytes rather than
+                    // re-peeking the same already-buffered ones, so a peer
+                    // that never completes a header (or closes early) can't
+                    // spin this loop at 100% CPU.
+                    if self.connection.read_buf(&mut self.read_buffer).await? == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "connection closed before a complete proxy header was received",
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn kick(&mut self, reason: TextComponent) {
+        let _ = self.send_raw(reason.to_pretty_console().as_bytes()).await;
+        self.closed = true;
+    }
+
+    pub async fn send_raw(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.connection.write_all(packet).await
+    }
+
+    /// Reads whatever is available into `read_buffer` and hands it to
+    /// `decode_buffered_packets`.
+    ///
+    /// Cancel-safe: `read_buf` only appends bytes it has actually read, so if
+    /// this future is dropped mid-await (e.g. `handle_connection`'s `select!`
+    /// picked another branch first) nothing is lost — the next call just
+    /// keeps filling the same buffer from where it left off.
+    pub async fn process_packets(&mut self, _server: &Server) {
+        match self.connection.read_buf(&mut self.read_buffer).await {
+            Ok(0) => self.closed = true,
+            Ok(_) => self.decode_buffered_packets(),
+            Err(e) => {
+                log::warn!("error reading from {}: {e}", self.address);
+                self.closed = true;
+            }
+        }
+    }
+
+    /// Synchronous on purpose: everything after the cancel-safe read above
+    /// must not itself await, or cancelling mid-decode could drop an
+    /// already-framed packet instead of just unread bytes.
+    fn decode_buffered_packets(&mut self) {
+        // No packet framing or handshake decoding is done here: any buffered
+        // bytes promote the connection to a player and are then discarded.
+        // Before that, under Bungee/Velocity mode, treat the buffered bytes as
+        // the handshake's server-address field and pull the forwarded
+        // identity out of it before the handshake is considered handled.
+        if self.proxy_mode == ProxyMode::Bungee && self.forwarded_profile.is_none() {
+            match proxy::parse_bungee_forwarding(&self.read_buffer) {
+                BungeeForwarding::Present(info) => {
+                    self.address = SocketAddr::new(info.ip, self.address.port());
+                    self.forwarded_profile = Some(info);
+                }
+                BungeeForwarding::Invalid => {
+                    log::warn!("closing {}: invalid bungee forwarding field", self.address);
+                    self.closed = true;
+                    return;
+                }
+                BungeeForwarding::Incomplete => {
+                    // The field hasn't fully arrived yet (it can land across
+                    // more than one read); wait for more bytes instead of
+                    // promoting to a player on a partial field, unless a peer
+                    // is just never going to send the rest of it.
+                    if self.read_buffer.len() > MAX_BUNGEE_FIELD_LEN {
+                        log::warn!(
+                            "closing {}: oversized bungee forwarding field",
+                            self.address
+                        );
+                        self.closed = true;
+                    }
+                    return;
+                }
+            }
+        }
+
+        if !self.read_buffer.is_empty() {
+            self.make_player = true;
+            self.read_buffer.clear();
+        }
+    }
+}
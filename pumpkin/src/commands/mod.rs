@@ -0,0 +1,32 @@
+use crate::server::Server;
+
+/// Where a command came from, and where its output should go. Shared by the
+/// console reader thread and RCON so both surfaces dispatch the same way.
+pub enum CommandSender<'a> {
+    Console,
+    Rcon(&'a mut String),
+}
+
+impl CommandSender<'_> {
+    fn send_message(&mut self, message: &str) {
+        match self {
+            CommandSender::Console => log::info!("{message}"),
+            CommandSender::Rcon(reply) => {
+                reply.push_str(message);
+                reply.push('\n');
+            }
+        }
+    }
+}
+
+/// Dispatches a single line of input from `sender`.
+pub async fn handle_command(sender: &mut CommandSender<'_>, command: &str, server: &Server) {
+    match command.trim() {
+        "" => {}
+        "list" => {
+            let (current, max) = server.connection_counts().await;
+            sender.send_message(&format!("{current}/{max} connections"));
+        }
+        other => sender.send_message(&format!("Unknown command: {other}")),
+    }
+}
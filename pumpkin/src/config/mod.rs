@@ -0,0 +1,74 @@
+use serde::Deserialize;
+
+/// Settings that affect what players can do; loaded once at startup from
+/// `configuration.toml`.
+#[derive(Deserialize, Clone)]
+pub struct BasicConfiguration {
+    pub server_address: String,
+    pub server_port: u16,
+    /// Maximum number of simultaneous connections (pre-login and players
+    /// alike) the accept loop admits before rejecting new sockets.
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+}
+
+fn default_max_connections() -> usize {
+    100
+}
+
+impl BasicConfiguration {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str(&content).unwrap_or_else(|_| Self {
+            server_address: "0.0.0.0".to_string(),
+            server_port: 25565,
+            max_connections: default_max_connections(),
+        })
+    }
+}
+
+/// Settings that affect how the server is run rather than gameplay itself;
+/// loaded once at startup from `features.toml`.
+#[derive(Deserialize, Clone, Default)]
+pub struct AdvancedConfiguration {
+    pub commands: CommandsConfig,
+    pub rcon: RCONConfig,
+    pub proxy: ProxyConfig,
+}
+
+impl AdvancedConfiguration {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str(&content).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct CommandsConfig {
+    pub use_console: bool,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct RCONConfig {
+    pub enabled: bool,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+/// Which ingress proxy protocol, if any, connections should be checked for
+/// before being handed to the handshake decoder.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    #[default]
+    Disabled,
+    HaProxy,
+    Bungee,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ProxyConfig {
+    pub mode: ProxyMode,
+}
@@ -0,0 +1,3 @@
+pub mod player;
+
+pub use player::Player;
@@ -0,0 +1,20 @@
+use crate::client::Client;
+use crate::server::{Server, Token};
+
+/// A connection that has completed login and joined the world.
+pub struct Player {
+    pub client: Client,
+    pub token: Token,
+}
+
+impl Player {
+    pub fn new(client: Client, token: Token) -> Self {
+        Self { client, token }
+    }
+
+    /// Mirrors `Client::process_packets`, but for play-state packets once
+    /// the connection has become a player.
+    pub async fn process_packets(&mut self, server: &Server) {
+        self.client.process_packets(server).await;
+    }
+}
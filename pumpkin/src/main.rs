@@ -1,20 +1,21 @@
-#![allow(clippy::await_holding_refcell_ref)]
-
-use mio::net::TcpListener;
-use mio::{Events, Interest, Poll, Token};
 use std::io::{self};
+use std::net::SocketAddr;
+use std::time::Duration;
 
 use client::Client;
 use commands::handle_command;
-use config::AdvancedConfiguration;
+use config::{AdvancedConfiguration, ProxyMode};
 
-use std::{collections::HashMap, rc::Rc, thread};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 
-use client::interrupted;
 use config::BasicConfiguration;
 use server::Server;
 
-// Setup some tokens to allow us to identify which event is for which socket.
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
 
 pub mod client;
 pub mod commands;
@@ -31,7 +32,6 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 
 #[cfg(not(target_os = "wasi"))]
 fn main() -> io::Result<()> {
-    use entity::player::Player;
     use pumpkin_core::text::{color::NamedColor, TextComponent};
 
     #[cfg(feature = "dhat-heap")]
@@ -43,182 +43,233 @@ fn main() -> io::Result<()> {
         .build()
         .unwrap();
 
-    ctrlc::set_handler(|| {
+    let shutdown_requested = Arc::new(AtomicBool::new(false));
+
+    let ctrlc_shutdown = shutdown_requested.clone();
+    ctrlc::set_handler(move || {
         log::warn!(
             "{}",
             TextComponent::text("Stopping Server")
                 .color_named(NamedColor::Red)
                 .to_pretty_console()
         );
-        std::process::exit(0);
+        ctrlc_shutdown.store(true, Ordering::SeqCst);
     })
     .unwrap();
     // ensure rayon is built outside of tokio scope
     rayon::ThreadPoolBuilder::new().build_global().unwrap();
-    rt.block_on(async {
-        const SERVER: Token = Token(0);
-        use std::{cell::RefCell, time::Instant};
-
-        use rcon::RCONServer;
-
-        let time = Instant::now();
-        let basic_config = BasicConfiguration::load("configuration.toml");
-
-        let advanced_configuration = AdvancedConfiguration::load("features.toml");
-
-        simple_logger::SimpleLogger::new().init().unwrap();
-
-        // Create a poll instance.
-        let mut poll = Poll::new()?;
-        // Create storage for events.
-        let mut events = Events::with_capacity(128);
-
-        // Setup the TCP server socket.
-
-        let addr = format!(
-            "{}:{}",
-            basic_config.server_address, basic_config.server_port
-        )
-        .parse()
-        .unwrap();
+    rt.block_on(run(shutdown_requested))
+}
 
-        let mut listener = TcpListener::bind(addr)?;
+/// Accepts connections and spawns one task per connection. Each task owns its
+/// `Client`, and later the `Player` it becomes, end-to-end, doing async I/O
+/// directly on its own `TcpStream` instead of going through a shared `Poll`.
+/// `Server` is shared behind a plain `Arc`, not a lock around the whole
+/// struct: its own mutable state (the connection registry) has its own lock
+/// scoped to just that operation, so the hot packet-processing path below
+/// never blocks on, or blocks, anything else connection tasks are doing.
+async fn run(shutdown_requested: Arc<AtomicBool>) -> io::Result<()> {
+    use std::time::Instant;
+
+    use rcon::RCONServer;
+
+    let time = Instant::now();
+    let basic_config = BasicConfiguration::load("configuration.toml");
+    let advanced_configuration = AdvancedConfiguration::load("features.toml");
+
+    simple_logger::SimpleLogger::new().init().unwrap();
+
+    let addr: SocketAddr = format!(
+        "{}:{}",
+        basic_config.server_address, basic_config.server_port
+    )
+    .parse()
+    .unwrap();
 
-        // Register the server with poll we can receive events for it.
-        poll.registry()
-            .register(&mut listener, SERVER, Interest::READABLE)?;
+    let listener = TcpListener::bind(addr).await?;
+
+    let use_console = advanced_configuration.commands.use_console;
+    let rcon = advanced_configuration.rcon.clone();
+    let proxy_mode = advanced_configuration.proxy.mode;
+    let max_connections = basic_config.max_connections;
+    let live_connections = Arc::new(AtomicUsize::new(0));
+
+    let server = Arc::new(Server::new((basic_config, advanced_configuration)));
+    log::info!("Started Server took {}ms", time.elapsed().as_millis());
+    log::info!("You now can connect to the server, Listening on {}", addr);
+
+    if use_console {
+        let console_shutdown = shutdown_requested.clone();
+        let console_server = server.clone();
+        // The console reader runs on a plain OS thread (blocking stdin reads
+        // don't mix with the async runtime); bridge back into it with the
+        // runtime handle to run the same async command dispatcher RCON uses.
+        let rt_handle = tokio::runtime::Handle::current();
+        thread::spawn(move || {
+            let stdin = std::io::stdin();
+            loop {
+                let mut out = String::new();
+                stdin
+                    .read_line(&mut out)
+                    .expect("Failed to read console line");
+
+                if out.trim() == "stop" {
+                    console_shutdown.store(true, Ordering::SeqCst);
+                } else if !out.is_empty() {
+                    rt_handle.block_on(handle_command(
+                        &mut commands::CommandSender::Console,
+                        &out,
+                        &console_server,
+                    ));
+                }
+            }
+        });
+    }
+    if rcon.enabled {
+        let rcon_shutdown = shutdown_requested.clone();
+        let rcon_server = server.clone();
+        tokio::spawn(async move {
+            RCONServer::new(&rcon, rcon_shutdown, rcon_server).await.unwrap();
+        });
+    }
+
+    // Tells every connection task to disconnect its player and return on shutdown.
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    let shutdown_message = TextComponent::text("Server closed").color_named(NamedColor::Red);
+
+    let mut connection_tasks = JoinSet::new();
+
+    loop {
+        if shutdown_requested.load(Ordering::SeqCst) {
+            log::info!(
+                "Shutting down, disconnecting {} connection(s)...",
+                live_connections.load(Ordering::SeqCst)
+            );
+            let _ = shutdown_tx.send(());
+            while connection_tasks.join_next().await.is_some() {}
+            server.save();
+            return Ok(());
+        }
 
-        // Unique token for each incoming connection.
-        let mut unique_token = Token(SERVER.0 + 1);
+        let accepted = tokio::time::timeout(Duration::from_millis(250), listener.accept()).await;
+        let (connection, address) = match accepted {
+            Ok(Ok(pair)) => pair,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => continue, // timed out waiting for a connection, re-check shutdown
+        };
 
-        let use_console = advanced_configuration.commands.use_console;
-        let rcon = advanced_configuration.rcon.clone();
+        if let Err(e) = connection.set_nodelay(true) {
+            log::warn!("failed to set TCP_NODELAY {e}");
+        }
 
-        let mut clients: HashMap<Token, Client> = HashMap::new();
-        let mut players: HashMap<Rc<Token>, Rc<RefCell<Player>>> = HashMap::new();
+        if live_connections.load(Ordering::SeqCst) >= max_connections {
+            log::warn!(
+                "Rejecting connection from {address}: server full ({}/{max_connections})",
+                live_connections.load(Ordering::SeqCst)
+            );
+            let (reject_tx, _) = mpsc::unbounded_channel();
+            let mut client = Client::new(connection, address, reject_tx);
+            client
+                .kick(TextComponent::text("Server is full").color_named(NamedColor::Red))
+                .await;
+            continue;
+        }
 
-        let mut server = Server::new((basic_config, advanced_configuration));
-        log::info!("Started Server took {}ms", time.elapsed().as_millis());
-        log::info!("You now can connect to the server, Listening on {}", addr);
+        log::info!("Accepted connection from: {address}");
+
+        live_connections.fetch_add(1, Ordering::SeqCst);
+
+        let server = server.clone();
+        let live_connections = live_connections.clone();
+        let shutdown_rx = shutdown_tx.subscribe();
+        let shutdown_message = shutdown_message.clone();
+
+        connection_tasks.spawn(async move {
+            handle_connection(
+                connection,
+                address,
+                &server,
+                proxy_mode,
+                shutdown_rx,
+                shutdown_message,
+            )
+            .await;
+
+            live_connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
 
-        if use_console {
-            thread::spawn(move || {
-                let stdin = std::io::stdin();
-                loop {
-                    let mut out = String::new();
-                    stdin
-                        .read_line(&mut out)
-                        .expect("Failed to read console line");
+/// Drives a single connection from raw `Client` through to a disconnected
+/// `Player`, returning once the socket closes or shutdown is signalled.
+///
+/// Each connection owns an outbound queue: `Server::broadcast`/`broadcast_except`
+/// push an encoded packet onto every subscriber's queue instead of reaching into
+/// a shared player map, and this task is the only thing that ever drains its own
+/// queue and writes to the socket.
+async fn handle_connection(
+    connection: TcpStream,
+    address: SocketAddr,
+    server: &Arc<Server>,
+    proxy_mode: ProxyMode,
+    mut shutdown_rx: broadcast::Receiver<()>,
+    shutdown_message: pumpkin_core::text::TextComponent,
+) {
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let mut client = Client::new(connection, address, outbound_tx.clone());
+    // Registered for the whole connection, not just once it becomes a
+    // player, so broadcasts reach clients still in the login handshake too.
+    let token = server.register_connection(outbound_tx).await;
+
+    if let Err(e) = client.apply_proxy_header(proxy_mode).await {
+        log::warn!("error reading proxy header from {address}: {e}");
+        server.disconnect(token).await;
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                client.kick(shutdown_message).await;
+                server.disconnect(token).await;
+                return;
+            }
+            Some(packet) = outbound_rx.recv() => {
+                let _ = client.send_raw(&packet).await;
+            }
+            _ = client.process_packets(server) => {}
+        }
 
-                    if !out.is_empty() {
-                        handle_command(&mut commands::CommandSender::Console, &out);
-                    }
-                }
-            });
+        if client.closed {
+            server.disconnect(token).await;
+            return;
         }
-        if rcon.enabled {
-            tokio::spawn(async move {
-                RCONServer::new(&rcon).await.unwrap();
-            });
+        if client.make_player {
+            break;
         }
-        loop {
-            if let Err(err) = poll.poll(&mut events, None) {
-                if interrupted(&err) {
-                    continue;
-                }
-                return Err(err);
-            }
+    }
 
-            for event in events.iter() {
-                match event.token() {
-                    SERVER => loop {
-                        // Received an event for the TCP server socket, which
-                        // indicates we can accept an connection.
-                        let (mut connection, address) = match listener.accept() {
-                            Ok((connection, address)) => (connection, address),
-                            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                                // If we get a `WouldBlock` error we know our
-                                // listener has no more incoming connections queued,
-                                // so we can return to polling and wait for some
-                                // more.
-                                break;
-                            }
-                            Err(e) => {
-                                // If it was any other kind of error, something went
-                                // wrong and we terminate with an error.
-                                return Err(e);
-                            }
-                        };
-                        if let Err(e) = connection.set_nodelay(true) {
-                            log::warn!("failed to set TCP_NODELAY {e}");
-                        }
-
-                        log::info!("Accepted connection from: {}", address);
-
-                        let token = next(&mut unique_token);
-                        poll.registry().register(
-                            &mut connection,
-                            token,
-                            Interest::READABLE.add(Interest::WRITABLE),
-                        )?;
-                        let rc_token = Rc::new(token);
-                        let client = Client::new(Rc::clone(&rc_token), connection, addr);
-                        clients.insert(token, client);
-                    },
-
-                    token => {
-                        // Poll Players
-                        let done = if let Some(player) = players.get_mut(&token) {
-                            let mut player = player.borrow_mut();
-                            player.client.poll(event).await;
-                            player.process_packets(&mut server);
-                            player.client.closed
-                        } else {
-                            false
-                        };
-
-                        if done {
-                            if let Some(player) = players.remove(&token) {
-                                server.remove_player(&token);
-                                let mut player = player.borrow_mut();
-                                poll.registry().deregister(&mut player.client.connection)?;
-                            }
-                        }
-
-                        // Poll current Clients (non players)
-                        // Maybe received an event for a TCP connection.
-                        let (done, make_player) = if let Some(client) = clients.get_mut(&token) {
-                            client.poll(event).await;
-                            client.process_packets(&mut server).await;
-                            (client.closed, client.make_player)
-                        } else {
-                            // Sporadic events happen, we can safely ignore them.
-                            (false, false)
-                        };
-                        if done || make_player {
-                            if let Some(mut client) = clients.remove(&token) {
-                                if done {
-                                    poll.registry().deregister(&mut client.connection)?;
-                                } else if make_player {
-                                    let token = client.token.clone();
-                                    let player = server.add_player(token.clone(), client);
-                                    players.insert(token, player.clone());
-                                    let mut player = player.borrow_mut();
-                                    server.spawn_player(&mut player).await;
-                                }
-                            }
-                        }
-                    }
-                }
+    let mut player = server.add_player(client, token).await;
+    server.spawn_player(&mut player).await;
+
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                player.client.kick(shutdown_message).await;
+                server.remove_player(&player).await;
+                return;
+            }
+            Some(packet) = outbound_rx.recv() => {
+                let _ = player.client.send_raw(&packet).await;
             }
+            _ = player.process_packets(server) => {}
         }
-    })
-}
 
-fn next(current: &mut Token) -> Token {
-    let next = current.0;
-    current.0 += 1;
-    Token(next)
+        if player.client.closed {
+            server.remove_player(&player).await;
+            return;
+        }
+    }
 }
 
 #[cfg(target_os = "wasi")]
@@ -0,0 +1,248 @@
+use std::net::{IpAddr, SocketAddr};
+
+/// Recovers the real client address when Pumpkin is reached through a proxy.
+///
+/// Two ingress modes are supported, selected via `AdvancedConfiguration`'s
+/// `proxy.mode`:
+/// - the HAProxy PROTOCOL header (v1, text form), used by TCP/L4 load balancers.
+/// - BungeeCord/Velocity player-info forwarding, carried in the handshake's
+///   server-address field.
+///
+/// Both are parsed as soon as the bytes are available (the HAProxy header before
+/// the handshake packet, the forwarding payload as part of it) and the resolved
+/// address/profile are applied to the `Client` before any further packets are
+/// processed, so everything downstream sees the real player instead of the proxy.
+
+/// The client identity BungeeCord/Velocity forwarded alongside the real
+/// source IP. Forwarding only ever carries the IP, never the source port, so
+/// the caller is expected to pair `ip` with the port from the socket it
+/// actually accepted on rather than inventing one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardedPlayerInfo {
+    pub ip: IpAddr,
+    pub uuid: String,
+    pub properties: String,
+}
+
+/// The outcome of looking for a HAProxy PROTOCOL v1 header at the start of a
+/// buffer that may not yet hold a full line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaProxyHeader {
+    /// A well-formed header occupying the first `usize` bytes of the buffer.
+    Present(SocketAddr, usize),
+    /// `buf` does not yet contain a `\r\n` and is still shorter than the
+    /// 107-byte v1 maximum; more bytes may still turn it into a header (or
+    /// not) once they arrive.
+    Incomplete,
+    /// `buf` already contains a full line and it is not a PROXY header, or it
+    /// holds 107 or more bytes with no `\r\n`.
+    NotProxy,
+}
+
+/// Parses a HAProxy PROTOCOL v1 header from the start of `buf`.
+///
+/// Expects the text form `PROXY TCP4 <src> <dst> <sport> <dport>\r\n` (or
+/// `TCP6`). Distinguishes "not buffered yet" from "not a PROXY header" so the
+/// caller knows whether to keep reading or give up and treat `buf` as a
+/// plain handshake.
+pub fn parse_haproxy_v1(buf: &[u8]) -> HaProxyHeader {
+    let Some(header_end) = buf.windows(2).position(|w| w == b"\r\n").map(|i| i + 2) else {
+        // The PROXY protocol caps the v1 header at 107 bytes; past that a
+        // missing \r\n means this was never one.
+        return if buf.len() < 107 {
+            HaProxyHeader::Incomplete
+        } else {
+            HaProxyHeader::NotProxy
+        };
+    };
+
+    (|| {
+        let line = std::str::from_utf8(&buf[..header_end - 2]).ok()?;
+        let mut parts = line.split(' ');
+        if parts.next()? != "PROXY" {
+            return None;
+        }
+        let proto = parts.next()?;
+        if proto != "TCP4" && proto != "TCP6" {
+            return None;
+        }
+        let src_ip: IpAddr = parts.next()?.parse().ok()?;
+        let _dst_ip = parts.next()?;
+        let src_port: u16 = parts.next()?.parse().ok()?;
+
+        Some(HaProxyHeader::Present(
+            SocketAddr::new(src_ip, src_port),
+            header_end,
+        ))
+    })()
+    .unwrap_or(HaProxyHeader::NotProxy)
+}
+
+/// The outcome of looking for a Bungee/Velocity forwarding field at the start
+/// of a buffer that may not yet hold all of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BungeeForwarding {
+    /// A well-formed `host\0clientIp\0uuid\0properties` field.
+    Present(ForwardedPlayerInfo),
+    /// Fewer than the 3 required `\0` separators have arrived yet; more
+    /// bytes may still complete it.
+    Incomplete,
+    /// All required fields are delimited, but they don't form a valid
+    /// forwarding payload (bad IP or empty uuid) — more bytes won't fix it.
+    Invalid,
+}
+
+/// Parses legacy BungeeCord IP forwarding out of the handshake's server-address
+/// field: `host\0clientIp\0uuid\0properties`. Velocity falls back to the same
+/// legacy format unless its modern, signed forwarding mode is configured; that
+/// mode carries a separate signed payload and is intentionally not handled here.
+///
+/// Takes raw bytes, not `&str`: `buf` may be a partial read whose tail cuts a
+/// multi-byte UTF-8 character in the (still-growing) trailing `properties`
+/// field, which must not read as `Invalid`. Once all 3 separators are present
+/// the result is `Present`, with `properties` holding only what has arrived so
+/// far (a cut character becomes U+FFFD).
+pub fn parse_bungee_forwarding(buf: &[u8]) -> BungeeForwarding {
+    // The first three fields (host, clientIp, uuid) are all that decide
+    // validity, and `properties` never affects it, so checking for all 3
+    // separators is exactly "every field up to uuid has fully arrived".
+    if buf.iter().filter(|&&b| b == 0).count() < 3 {
+        return BungeeForwarding::Incomplete;
+    }
+
+    let text = String::from_utf8_lossy(buf);
+    let mut fields = text.splitn(4, '\0');
+    let _host = fields.next();
+    let client_ip = fields.next().unwrap_or_default();
+    let uuid = fields.next().unwrap_or_default();
+    let properties = fields.next().unwrap_or_default().to_owned();
+
+    if uuid.is_empty() {
+        return BungeeForwarding::Invalid;
+    }
+
+    let Ok(ip) = client_ip.parse::<IpAddr>() else {
+        return BungeeForwarding::Invalid;
+    };
+
+    BungeeForwarding::Present(ForwardedPlayerInfo {
+        ip,
+        uuid: uuid.to_owned(),
+        properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn haproxy_v1_tcp4() {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nextra";
+        match parse_haproxy_v1(header) {
+            HaProxyHeader::Present(address, header_len) => {
+                assert_eq!(address, "192.168.0.1:56324".parse().unwrap());
+                assert_eq!(&header[..header_len], b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n");
+            }
+            other => panic!("expected Present, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn haproxy_v1_tcp6() {
+        let header = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n";
+        match parse_haproxy_v1(header) {
+            HaProxyHeader::Present(address, header_len) => {
+                assert_eq!(address, "[2001:db8::1]:56324".parse().unwrap());
+                assert_eq!(header_len, header.len());
+            }
+            other => panic!("expected Present, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn haproxy_v1_partial_header_is_incomplete() {
+        assert_eq!(parse_haproxy_v1(b"PROXY TCP4 192.168"), HaProxyHeader::Incomplete);
+        assert_eq!(parse_haproxy_v1(b""), HaProxyHeader::Incomplete);
+    }
+
+    #[test]
+    fn haproxy_v1_non_proxy_line_is_not_proxy() {
+        assert_eq!(
+            parse_haproxy_v1(b"GET / HTTP/1.1\r\n"),
+            HaProxyHeader::NotProxy
+        );
+    }
+
+    #[test]
+    fn haproxy_v1_overlong_line_without_crlf_is_not_proxy() {
+        let buf = vec![b'a'; 200];
+        assert_eq!(parse_haproxy_v1(&buf), HaProxyHeader::NotProxy);
+    }
+
+    #[test]
+    fn bungee_forwarding_parses_all_fields() {
+        let buf = b"play.example.com\x0010.0.0.5\x00some-uuid\x00some-properties";
+        match parse_bungee_forwarding(buf) {
+            BungeeForwarding::Present(info) => {
+                assert_eq!(info.ip, "10.0.0.5".parse().unwrap());
+                assert_eq!(info.uuid, "some-uuid");
+                assert_eq!(info.properties, "some-properties");
+            }
+            other => panic!("expected Present, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bungee_forwarding_defaults_missing_properties() {
+        let buf = b"play.example.com\x0010.0.0.5\x00some-uuid\x00";
+        match parse_bungee_forwarding(buf) {
+            BungeeForwarding::Present(info) => assert_eq!(info.properties, ""),
+            other => panic!("expected Present, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bungee_forwarding_rejects_empty_uuid() {
+        assert_eq!(
+            parse_bungee_forwarding(b"play.example.com\x0010.0.0.5\x00\x00"),
+            BungeeForwarding::Invalid
+        );
+    }
+
+    #[test]
+    fn bungee_forwarding_incomplete_before_all_separators_arrive() {
+        assert_eq!(
+            parse_bungee_forwarding(b"play.example.com"),
+            BungeeForwarding::Incomplete
+        );
+        assert_eq!(
+            parse_bungee_forwarding(b"play.example.com\x0010.0.0.5\x00some-uu"),
+            BungeeForwarding::Incomplete
+        );
+    }
+
+    #[test]
+    fn bungee_forwarding_rejects_unparseable_ip() {
+        assert_eq!(
+            parse_bungee_forwarding(b"play.example.com\x00not-an-ip\x00some-uuid\x00"),
+            BungeeForwarding::Invalid
+        );
+    }
+
+    #[test]
+    fn bungee_forwarding_tolerates_split_multibyte_properties() {
+        // A 2-byte UTF-8 character ('é' = 0xC3 0xA9) cut after its first byte,
+        // as a partial read might deliver it. Validity only hinges on the
+        // host/ip/uuid fields ahead of it, so this must not read as Invalid.
+        let buf = [
+            b"play.example.com\x0010.0.0.5\x00some-uuid\x00".as_slice(),
+            &[0xC3],
+        ]
+        .concat();
+        match parse_bungee_forwarding(&buf) {
+            BungeeForwarding::Present(_) => {}
+            other => panic!("expected Present, got {other:?}"),
+        }
+    }
+}
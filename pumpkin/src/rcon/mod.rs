@@ -0,0 +1,103 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::commands::{handle_command, CommandSender};
+use crate::config::RCONConfig;
+use crate::server::Server;
+
+/// A minimal, newline-delimited RCON listener: authenticate with the
+/// configured password, then dispatch each line through [`handle_command`],
+/// the same dispatcher the console uses. `stop` is handled here before
+/// dispatch: it sets the same shutdown flag Ctrl-C sets, so it takes the same
+/// graceful shutdown path.
+pub struct RCONServer;
+
+impl RCONServer {
+    pub async fn new(
+        config: &RCONConfig,
+        shutdown_requested: Arc<AtomicBool>,
+        server: Arc<Server>,
+    ) -> io::Result<Self> {
+        let addr: SocketAddr = config
+            .address
+            .parse()
+            .unwrap_or_else(|_| "0.0.0.0:25575".parse().unwrap());
+        let listener = TcpListener::bind(addr).await?;
+        log::info!("RCON listening on {addr}");
+
+        loop {
+            if shutdown_requested.load(Ordering::SeqCst) {
+                return Ok(Self);
+            }
+
+            let accepted = tokio::time::timeout(Duration::from_millis(250), listener.accept()).await;
+            let (connection, address) = match accepted {
+                Ok(Ok(pair)) => pair,
+                Ok(Err(e)) => return Err(e),
+                Err(_) => continue, // timed out waiting for a connection, re-check shutdown
+            };
+
+            let password = config.password.clone();
+            let shutdown_requested = shutdown_requested.clone();
+            let server = server.clone();
+            tokio::spawn(Self::handle_connection(
+                connection,
+                address,
+                password,
+                shutdown_requested,
+                server,
+            ));
+        }
+    }
+
+    async fn handle_connection(
+        connection: TcpStream,
+        address: SocketAddr,
+        password: String,
+        shutdown_requested: Arc<AtomicBool>,
+        server: Arc<Server>,
+    ) {
+        let (read_half, mut write_half) = connection.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+
+        let authenticated = match lines.next_line().await {
+            Ok(Some(line)) => line == password,
+            _ => false,
+        };
+        if !authenticated {
+            log::warn!("Rejected RCON connection from {address}: bad password");
+            let _ = write_half.write_all(b"Login failed\n").await;
+            return;
+        }
+
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return, // connection closed
+                Err(e) => {
+                    log::warn!("error reading RCON command from {address}: {e}");
+                    return;
+                }
+            };
+
+            if line.trim() == "stop" {
+                shutdown_requested.store(true, Ordering::SeqCst);
+                let _ = write_half.write_all(b"Stopping server\n").await;
+                return;
+            }
+
+            let mut reply = String::new();
+            handle_command(&mut CommandSender::Rcon(&mut reply), &line, &server).await;
+            if let Err(e) = write_half.write_all(reply.as_bytes()).await {
+                log::warn!("error writing RCON reply to {address}: {e}");
+                return;
+            }
+        }
+    }
+}
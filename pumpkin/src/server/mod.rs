@@ -0,0 +1,103 @@
+mod registry;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Mutex;
+
+pub use registry::Token;
+use registry::ConnectionRegistry;
+
+use crate::client::Client;
+use crate::config::{AdvancedConfiguration, BasicConfiguration};
+use crate::entity::Player;
+
+/// Shared server state. Connection bookkeeping lives behind its own lock
+/// (see [`ConnectionRegistry`]) so that tracking who is connected never has
+/// to wait on, or block, anything else the server is doing.
+pub struct Server {
+    pub basic_config: BasicConfiguration,
+    pub advanced_config: AdvancedConfiguration,
+    connections: Mutex<ConnectionRegistry>,
+    /// The accept loop's admission cap, kept here purely so RCON and console
+    /// commands have something to read without reaching past `Server`. Set
+    /// once at startup; the accept loop is the only thing that enforces it.
+    max_connections: usize,
+}
+
+impl Server {
+    pub fn new(config: (BasicConfiguration, AdvancedConfiguration)) -> Self {
+        let max_connections = config.0.max_connections;
+        Self {
+            basic_config: config.0,
+            advanced_config: config.1,
+            connections: Mutex::new(ConnectionRegistry::new()),
+            max_connections,
+        }
+    }
+
+    /// Registers a connection's outbound queue, returning the slab token to
+    /// hand back to [`Server::disconnect`] once the connection tears down.
+    pub async fn register_connection(
+        &self,
+        outbound_tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+    ) -> Token {
+        self.connections.lock().await.insert(outbound_tx)
+    }
+
+    /// Frees `token`, making it available to the next connection.
+    pub async fn disconnect(&self, token: Token) {
+        self.connections.lock().await.remove(token);
+    }
+
+    pub async fn connection_count(&self) -> usize {
+        self.connections.lock().await.len()
+    }
+
+    /// Pushes `packet` onto every connected subscriber's outbound queue,
+    /// pre-login clients and players alike.
+    pub async fn broadcast(&self, packet: Vec<u8>) {
+        self.connections.lock().await.broadcast(&packet);
+    }
+
+    /// Like [`Server::broadcast`], but skips the subscriber registered under
+    /// `except` (e.g. the sender of a chat message echoing it back to
+    /// everyone else).
+    pub async fn broadcast_except(&self, except: Token, packet: Vec<u8>) {
+        self.connections.lock().await.broadcast_except(except, &packet);
+    }
+
+    /// Current/maximum connection counts, for the `list` console and RCON
+    /// command.
+    pub async fn connection_counts(&self) -> (usize, usize) {
+        let current = self.connections.lock().await.len();
+        (current, self.max_connections)
+    }
+
+    /// Promotes a logged-in `Client` to a `Player`, keeping the
+    /// broadcast-registry `token` it was already registered under when the
+    /// connection first came in, rather than handing out a second one.
+    ///
+    /// If the client carries a proxy-forwarded identity, online-mode auth
+    /// (not yet implemented in this snapshot) would be skipped here since
+    /// the proxy already verified it with Mojang.
+    pub async fn add_player(&self, client: Client, token: Token) -> Player {
+        if let Some(profile) = &client.forwarded_profile {
+            log::info!(
+                "Skipping online-mode auth for {}: identity forwarded by proxy (uuid {})",
+                client.address, profile.uuid
+            );
+        }
+        Player::new(client, token)
+    }
+
+    pub async fn remove_player(&self, player: &Player) {
+        self.disconnect(player.token).await;
+    }
+
+    pub async fn spawn_player(&self, _player: &mut Player) {
+        // World-join/spawn packets would be sent from here.
+    }
+
+    pub fn save(&self) {
+        log::info!("Saving world state before shutdown");
+    }
+}
@@ -0,0 +1,80 @@
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A reusable index into a [`ConnectionRegistry`].
+///
+/// Tokens are recycled: once a connection disconnects, its token is handed
+/// back out to the next connection instead of growing forever, which is the
+/// property a plain monotonic counter was missing.
+pub type Token = usize;
+
+/// Slab of live connections' outbound queues, keyed by a recyclable
+/// [`Token`].
+///
+/// This is the single place that knows about every connected socket: it
+/// backs connection counting (`len`) and fan-out (broadcast), so there is
+/// exactly one list to keep in sync instead of one per feature.
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    slots: Vec<Option<UnboundedSender<Vec<u8>>>>,
+    free: Vec<Token>,
+    len: usize,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a new connection's outbound queue, reusing a freed token when
+    /// one is available.
+    pub fn insert(&mut self, outbound_tx: UnboundedSender<Vec<u8>>) -> Token {
+        self.len += 1;
+        if let Some(token) = self.free.pop() {
+            self.slots[token] = Some(outbound_tx);
+            token
+        } else {
+            self.slots.push(Some(outbound_tx));
+            self.slots.len() - 1
+        }
+    }
+
+    /// Frees `token` so it can be handed to the next connection.
+    pub fn remove(&mut self, token: Token) {
+        if let Some(slot) = self.slots.get_mut(token) {
+            if slot.take().is_some() {
+                self.len -= 1;
+                self.free.push(token);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes `packet` onto every live subscriber's outbound queue. A send
+    /// failing just means that connection's task is already tearing down
+    /// its receiver; its slot is reclaimed the next time `remove` runs for it.
+    pub fn broadcast(&self, packet: &[u8]) {
+        for slot in self.slots.iter().flatten() {
+            let _ = slot.send(packet.to_vec());
+        }
+    }
+
+    /// Like [`ConnectionRegistry::broadcast`], but skips the subscriber
+    /// registered under `except`.
+    pub fn broadcast_except(&self, except: Token, packet: &[u8]) {
+        for (token, slot) in self.slots.iter().enumerate() {
+            if token == except {
+                continue;
+            }
+            if let Some(tx) = slot {
+                let _ = tx.send(packet.to_vec());
+            }
+        }
+    }
+}